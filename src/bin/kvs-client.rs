@@ -0,0 +1,79 @@
+use kvs::{KvsClient, Result};
+use std::net::SocketAddr;
+use structopt::StructOpt;
+
+const DEFAULT_ADDR: &str = "127.0.0.1:4000";
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = env!("CARGO_PKG_NAME"), version = env!("CARGO_PKG_VERSION"), author = env!("CARGO_PKG_AUTHORS"), about = "talks to a kvs-server over TCP")]
+enum Opt {
+    #[structopt(name = "get", about = "gets a value given a key")]
+    Get {
+        #[structopt(required = true, help = "Key")]
+        key: String,
+        #[structopt(
+            long,
+            default_value = DEFAULT_ADDR,
+            help = "Server address, e.g. 127.0.0.1:4000"
+        )]
+        addr: SocketAddr,
+    },
+    #[structopt(name = "set", about = "sets a value given a key")]
+    Set {
+        #[structopt(required = true, help = "Key")]
+        key: String,
+        #[structopt(required = true, help = "Value")]
+        value: String,
+        #[structopt(
+            long,
+            default_value = DEFAULT_ADDR,
+            help = "Server address, e.g. 127.0.0.1:4000"
+        )]
+        addr: SocketAddr,
+    },
+    #[structopt(name = "rm", about = "removes a value, given its key")]
+    Rm {
+        #[structopt(required = true, help = "Key")]
+        key: String,
+        #[structopt(
+            long,
+            default_value = DEFAULT_ADDR,
+            help = "Server address, e.g. 127.0.0.1:4000"
+        )]
+        addr: SocketAddr,
+    },
+}
+
+fn main() -> Result<()> {
+    match Opt::from_args() {
+        Opt::Get { key, addr } => get(key, addr),
+        Opt::Set { key, value, addr } => set(key, value, addr),
+        Opt::Rm { key, addr } => rm(key, addr),
+    }
+}
+
+fn get(key: String, addr: SocketAddr) -> Result<()> {
+    let mut client = KvsClient::connect(addr)?;
+
+    match client.get(key)? {
+        Some(value) => println!("{}", value),
+        None => println!("Key not found"),
+    }
+
+    Ok(())
+}
+
+fn set(key: String, value: String, addr: SocketAddr) -> Result<()> {
+    let mut client = KvsClient::connect(addr)?;
+
+    client.set(key, value)
+}
+
+fn rm(key: String, addr: SocketAddr) -> Result<()> {
+    let mut client = KvsClient::connect(addr)?;
+
+    client.remove(key).or_else(|err| {
+        println!("{}", err);
+        Err(err)
+    })
+}
@@ -0,0 +1,25 @@
+use kvs::{KvStore, KvsServer, Result};
+use std::net::SocketAddr;
+use structopt::StructOpt;
+
+const DEFAULT_ADDR: &str = "127.0.0.1:4000";
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = env!("CARGO_PKG_NAME"), version = env!("CARGO_PKG_VERSION"), author = env!("CARGO_PKG_AUTHORS"), about = "serves a kvs store over TCP")]
+struct Opt {
+    #[structopt(
+        long,
+        default_value = DEFAULT_ADDR,
+        help = "Listen address, e.g. 127.0.0.1:4000"
+    )]
+    addr: SocketAddr,
+}
+
+fn main() -> Result<()> {
+    let opt = Opt::from_args();
+
+    let current_directory = std::env::current_dir()?;
+    let store = KvStore::<String, String>::open(current_directory)?;
+
+    KvsServer::new(store).run(opt.addr)
+}
@@ -33,7 +33,7 @@ fn main() -> Result<()> {
 
 fn get(key: String) -> Result<()> {
     let current_directory = std::env::current_dir()?;
-    let store = KvStore::open(current_directory)?;
+    let store = KvStore::<String, String>::open_read_only(current_directory)?;
 
     store.get(key).and_then(|opt| {
         match opt {
@@ -46,14 +46,14 @@ fn get(key: String) -> Result<()> {
 
 fn set(key: String, value: String) -> Result<()> {
     let current_directory = std::env::current_dir()?;
-    let mut store = KvStore::open(current_directory)?;
+    let mut store = KvStore::<String, String>::open(current_directory)?;
 
     store.set(key, value)
 }
 
 fn rm(key: String) -> Result<()> {
     let current_directory = std::env::current_dir()?;
-    let mut store = KvStore::open(current_directory)?;
+    let mut store = KvStore::<String, String>::open(current_directory)?;
 
     store.remove(key).or_else(|err| {
         println!("{}", err);
@@ -0,0 +1,57 @@
+//! A TCP client for [`crate::server::KvsServer`], mirroring the local
+//! `KvStore` API over the wire.
+
+use crate::protocol::{read_message, write_message, Request, Response};
+use crate::{KvError, Result};
+use std::net::{TcpStream, ToSocketAddrs};
+
+/// Connects to a running `KvsServer` and issues `get`/`set`/`remove`
+/// requests over TCP.
+/// ```rust,no_run
+/// use kvs::KvsClient;
+/// let mut client = KvsClient::connect("127.0.0.1:4000").unwrap();
+/// client.set("Hello".to_owned(), "World".to_owned()).unwrap();
+/// ```
+pub struct KvsClient {
+    stream: TcpStream,
+}
+
+impl KvsClient {
+    /// Connects to a `KvsServer` listening at `addr`.
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<KvsClient> {
+        Ok(KvsClient {
+            stream: TcpStream::connect(addr)?,
+        })
+    }
+
+    /// Gets the value for `key`.
+    pub fn get(&mut self, key: String) -> Result<Option<String>> {
+        match self.send(Request::Get { key })? {
+            Response::Ok(value) => Ok(value),
+            Response::Err(msg) => Err(KvError::KvError(msg)),
+        }
+    }
+
+    /// Sets `key` to `value`.
+    pub fn set(&mut self, key: String, value: String) -> Result<()> {
+        match self.send(Request::Set { key, value })? {
+            Response::Ok(_) => Ok(()),
+            Response::Err(msg) => Err(KvError::KvError(msg)),
+        }
+    }
+
+    /// Removes `key`.
+    pub fn remove(&mut self, key: String) -> Result<()> {
+        match self.send(Request::Remove { key })? {
+            Response::Ok(_) => Ok(()),
+            Response::Err(msg) => Err(KvError::KvError(msg)),
+        }
+    }
+
+    fn send(&mut self, request: Request) -> Result<Response> {
+        write_message(&mut self.stream, &serde_json::to_vec(&request)?)?;
+        let response = read_message(&mut self.stream)?;
+
+        Ok(serde_json::from_slice(&response)?)
+    }
+}
@@ -3,18 +3,30 @@
 #![deny(missing_docs)]
 #![feature(seek_convenience)]
 
+mod protocol;
+pub mod client;
+pub mod server;
+
+pub use client::KvsClient;
+pub use server::KvsServer;
+
 use failure::Fail;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fs::{File, OpenOptions};
+use std::hash::Hash;
 use std::io::{self, BufRead, Seek, Write};
 use std::iter::FromIterator;
+use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 
 #[allow(clippy::identity_op)]
 const LOG_FILE_SIZE: u64 = 1 * 1024 * 1024; // 1MB
-const LOG_COMPACTION_COUNT: u32 = 10;
+/// Fraction of total on-disk bytes that must be stale (overwritten or
+/// removed) before a compaction rewrite is triggered.
+const COMPACTION_THRESHOLD: f64 = 0.5;
 
 /// kvs Error structure
 #[derive(Debug, Fail)]
@@ -58,55 +70,213 @@ impl From<std::num::ParseIntError> for KvError {
 /// Aliases standard Result to always have a KvError error component
 pub type Result<T> = std::result::Result<T, KvError>;
 
-/// Represents a key-value store
+/// Controls how hard `set`/`remove` push each log append to stable storage
+/// before returning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DurabilityMode {
+    /// Leave the write in the OS page cache; fastest, but a crash can lose
+    /// recently acknowledged writes.
+    None,
+    /// Flush the write out of any in-process buffering after every append.
+    Flush,
+    /// Flush and `fsync` the log file after every append, so an
+    /// acknowledged write survives a crash.
+    Fsync,
+}
+
+/// Controls the advisory lock `KvStore::open*` takes on the store directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenMode {
+    /// Takes an exclusive lock; only one process may hold this at a time.
+    /// `set` and `remove` are allowed.
+    ReadWrite,
+    /// Takes a shared lock, so any number of read-only opens (and no
+    /// read-write open) may coexist. `set` and `remove` return an error.
+    ReadOnly,
+}
+
+// Linux errno values `flock(2)` (and therefore `fs2`) surfaces when the
+// underlying filesystem doesn't implement advisory locking at all, e.g.
+// some NFS mounts without `lockd`. Rather than fail the whole `open`, we
+// treat these as "locking unavailable" and fall back to always doing a
+// full log replay, since a hint file can no longer be trusted to reflect
+// what every other process has written.
+const ENOLCK: i32 = 37;
+const ENOSYS: i32 = 38;
+const EOPNOTSUPP: i32 = 95;
+
+/// Represents a key-value store.
+///
+/// `K` and `V` are the key and value types persisted in the log; the `kvs`
+/// CLI uses `KvStore<String, String>`, but any type that can round-trip
+/// through serde works, e.g. `KvStore<u32, MyRecord>`.
 #[derive(Debug)]
-pub struct KvStore {
+pub struct KvStore<K, V>
+where
+    K: Serialize + DeserializeOwned + Hash + Eq + Clone,
+    V: Serialize + DeserializeOwned,
+{
     current_file_id: u32,
     path: PathBuf,
     file_ids: HashSet<u32>,
-    cache: HashMap<String, KvLocation>,
+    cache: HashMap<K, KvLocation>,
+    stale_bytes: u64,
+    durability: DurabilityMode,
+    open_mode: OpenMode,
+    // Held for the lifetime of the store purely so the advisory lock taken
+    // in `open_with_options` stays in effect; released when the fd closes.
+    _lock_file: File,
+    marker: PhantomData<V>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-enum KvCommand {
-    Set(String, String),
-    Get(String),
-    Rm(String),
+enum KvCommand<K, V> {
+    Set(K, V),
+    Get(K),
+    Rm(K),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct KvLocation {
     file_id: u32,
     offset: u64,
+    len: u64,
+}
+
+/// On-disk hint file: a snapshot of the in-memory index taken after
+/// compaction or on clean shutdown, so `open` doesn't have to replay every
+/// log segment from scratch. `highest_file_id` is the newest log segment
+/// folded into `index`; segments with a greater id are replayed on top of
+/// it, and a hint referencing a segment that no longer exists is ignored.
+/// The next log append removes the hint, so one that survives a crash never
+/// misses writes made after it. `stale_bytes` carries over the staleness
+/// ratio tracked so far, so `should_compact` doesn't forget it across a
+/// restart. `index` is a list of pairs rather than a map so that keys which
+/// JSON cannot use as object keys (tuples, structs) still round-trip.
+#[derive(Debug, Serialize, Deserialize)]
+struct HintFile<K>
+where
+    K: Hash + Eq,
+{
+    highest_file_id: u32,
+    index: Vec<(K, KvLocation)>,
+    stale_bytes: u64,
 }
 
-impl KvStore {
+impl<K, V> KvStore<K, V>
+where
+    K: Serialize + DeserializeOwned + Hash + Eq + Clone,
+    V: Serialize + DeserializeOwned,
+{
     /// Opens a file containing a KvStore
     /// ```rust
     /// use kvs::KvStore;
     /// use std::path::Path;
     /// let path = Path::new("");
-    /// match KvStore::open(&path) {
+    /// match KvStore::<String, String>::open(&path) {
+    ///   Ok(store) => println!("{:?}", store),
+    ///   Err(err) => println!("{:?}", err)
+    /// }
+    /// ```
+    pub fn open<A: AsRef<Path>>(path: A) -> Result<KvStore<K, V>> {
+        Self::open_with_durability(path, DurabilityMode::Flush)
+    }
+
+    /// Opens a store with an explicit [`DurabilityMode`], trading off write
+    /// latency against how much an acknowledged write survives a crash.
+    /// ```rust
+    /// use kvs::{DurabilityMode, KvStore};
+    /// use std::path::Path;
+    /// let path = Path::new("");
+    /// match KvStore::<String, String>::open_with_durability(&path, DurabilityMode::Fsync) {
+    ///   Ok(store) => println!("{:?}", store),
+    ///   Err(err) => println!("{:?}", err)
+    /// }
+    /// ```
+    pub fn open_with_durability<A: AsRef<Path>>(
+        path: A,
+        durability: DurabilityMode,
+    ) -> Result<KvStore<K, V>> {
+        Self::open_with_options(path, durability, OpenMode::ReadWrite)
+    }
+
+    /// Opens a store in [`OpenMode::ReadOnly`]: takes a shared lock instead
+    /// of an exclusive one, so it can coexist with other read-only opens,
+    /// but `set`/`remove` on the returned store return an error.
+    /// ```rust
+    /// use kvs::KvStore;
+    /// use std::path::Path;
+    /// let path = Path::new("");
+    /// match KvStore::<String, String>::open_read_only(&path) {
     ///   Ok(store) => println!("{:?}", store),
     ///   Err(err) => println!("{:?}", err)
     /// }
     /// ```
-    pub fn open<A: AsRef<Path>>(path: A) -> Result<KvStore> {
+    pub fn open_read_only<A: AsRef<Path>>(path: A) -> Result<KvStore<K, V>> {
+        Self::open_with_options(path, DurabilityMode::None, OpenMode::ReadOnly)
+    }
+
+    /// Opens a store with an explicit [`DurabilityMode`] and [`OpenMode`].
+    /// Takes an advisory lock on the store directory appropriate for
+    /// `open_mode`, failing with `KvError::KvError` if it's already held
+    /// incompatibly by another process. If the filesystem doesn't support
+    /// advisory locking at all (e.g. some NFS mounts), locking is skipped
+    /// and the store falls back to a full log replay instead of trusting a
+    /// hint file no lock is actually guarding.
+    pub fn open_with_options<A: AsRef<Path>>(
+        path: A,
+        durability: DurabilityMode,
+        open_mode: OpenMode,
+    ) -> Result<KvStore<K, V>> {
         let path = path.as_ref().to_path_buf();
+
+        let lock_file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(lock_path(&path))?;
+        let locking_supported = acquire_lock(&lock_file, open_mode)?;
+
         let file_ids = load_file_ids(&path)?;
         let current_file_id = file_ids.last().unwrap_or(&0);
 
-        let cache = file_ids
-            .iter()
-            .try_fold(HashMap::default(), |cache, log_id| {
-                update_cache(cache, *log_id, &path)
-            })?;
+        // A hint is only trustworthy if it doesn't point past the newest
+        // segment actually on disk; any append after it was written removes
+        // it (see `persist`). If the lock itself couldn't be taken out, no
+        // hint can be trusted either.
+        let hint = locking_supported
+            .then(|| load_hint::<K>(&path))
+            .flatten()
+            .filter(|(_, highest_file_id, _)| *highest_file_id <= *current_file_id);
+
+        let (cache, stale_bytes) = match hint {
+            Some((hinted_cache, highest_file_id, hinted_stale_bytes)) => file_ids
+                .iter()
+                .filter(|log_id| **log_id > highest_file_id)
+                .try_fold(
+                    (hinted_cache, hinted_stale_bytes),
+                    |(cache, stale_bytes), log_id| {
+                        update_cache::<K, V>(cache, stale_bytes, *log_id, &path)
+                    },
+                )?,
+            None => file_ids.iter().try_fold(
+                (HashMap::default(), 0u64),
+                |(cache, stale_bytes), log_id| {
+                    update_cache::<K, V>(cache, stale_bytes, *log_id, &path)
+                },
+            )?,
+        };
 
         Ok(KvStore {
             current_file_id: *current_file_id,
             file_ids: HashSet::from_iter(file_ids),
             path,
             cache,
+            stale_bytes,
+            durability,
+            open_mode,
+            _lock_file: lock_file,
+            marker: PhantomData,
         })
     }
 
@@ -116,10 +286,12 @@ impl KvStore {
     /// use kvs::KvStore;
     /// use std::path::Path;
     /// let path = Path::new(".");
-    /// let mut store = KvStore::open(&path).unwrap();
+    /// let mut store = KvStore::<String, String>::open(&path).unwrap();
     /// store.set("Hello".to_owned(), "World".to_owned()).unwrap();
     /// ```
-    pub fn set(&mut self, key: String, value: String) -> Result<()> {
+    pub fn set(&mut self, key: K, value: V) -> Result<()> {
+        self.require_read_write()?;
+
         let log_path = file_path(&self.path, self.current_file_id);
         let mut log_file = OpenOptions::new()
             .create(true)
@@ -127,28 +299,35 @@ impl KvStore {
             .open(&log_path)?;
 
         let offset = log_file.stream_len()?;
+        let mut writer = io::BufWriter::new(log_file);
 
-        let command = KvCommand::Set(key.to_owned(), value.to_owned());
+        let command = KvCommand::Set(key.clone(), value);
         let serialized = serde_json::to_string(&command)?;
 
-        writeln!(log_file, "{}", serialized)?;
+        writeln!(writer, "{}", serialized)?;
+        self.persist(&mut writer)?;
+        // Hand the append to the OS before `compact` may read it back.
+        drop(writer);
 
+        let len = serialized.len() as u64 + 1;
         let location = KvLocation {
             file_id: self.current_file_id,
             offset,
+            len,
         };
-        self.cache.insert(key, location);
+        if let Some(old_location) = self.cache.insert(key, location) {
+            self.stale_bytes += old_location.len;
+        }
 
         if log_path.metadata()?.len() > LOG_FILE_SIZE {
             self.file_ids.insert(self.current_file_id);
-
-            if self.current_file_id % LOG_COMPACTION_COUNT == 0 {
-                self.compact()?;
-            }
-
             self.current_file_id += 1;
         }
 
+        if self.should_compact()? {
+            self.compact()?;
+        }
+
         Ok(())
     }
 
@@ -157,30 +336,15 @@ impl KvStore {
     /// use kvs::KvStore;
     /// use std::path::Path;
     /// let path = Path::new(".");
-    /// let mut store = KvStore::open(&path).unwrap();
+    /// let mut store = KvStore::<String, String>::open(&path).unwrap();
     /// match store.get("Hello".to_owned()) {
     ///   Ok(opt) => println!("{:?}", opt.unwrap_or("nothing found.".to_string())),
     ///   Err(error) => println!("{:?}", error)
     /// }
     /// ```
-    pub fn get(&self, key: String) -> Result<Option<String>> {
+    pub fn get(&self, key: K) -> Result<Option<V>> {
         match self.cache.get(&key) {
-            Some(position) => {
-                let log_file = file_path(&self.path, position.file_id);
-                let mut buffered_reader = io::BufReader::new(File::open(log_file)?);
-
-                buffered_reader.seek(io::SeekFrom::Start(position.offset))?;
-
-                let mut line = String::default();
-                buffered_reader.read_line(&mut line)?;
-
-                let command = serde_json::from_str(&line.trim())?;
-
-                match command {
-                    KvCommand::Set(_key, value) => Ok(Some(value)),
-                    _ => Err(KvError::KvError("Inconsistent backing storage".to_string())),
-                }
-            }
+            Some(location) => self.read_value(location).map(Some),
             None => Ok(None),
         }
     }
@@ -191,22 +355,32 @@ impl KvStore {
     /// use kvs::KvStore;
     /// use std::path::Path;
     /// let path = Path::new(".");
-    /// let mut store = KvStore::open(&path).unwrap();
+    /// let mut store = KvStore::<String, String>::open(&path).unwrap();
     /// match store.remove("Hello".to_owned()) {
     ///   Ok(opt) => println!("done."),
     ///   Err(error) => println!("{:?}", error)
     /// }
     /// ```
-    pub fn remove(&mut self, key: String) -> Result<()> {
+    pub fn remove(&mut self, key: K) -> Result<()> {
+        self.require_read_write()?;
+
         match self.cache.remove(&key) {
-            Some(_) => {
+            Some(old_location) => {
                 let log_path = file_path(&self.path, self.current_file_id);
-                let mut log_file = OpenOptions::new().append(true).open(log_path)?;
+                let log_file = OpenOptions::new().append(true).open(log_path)?;
+                let mut writer = io::BufWriter::new(log_file);
 
-                let command = KvCommand::Rm(key.to_owned());
+                let command: KvCommand<K, V> = KvCommand::Rm(key);
                 let serialized = serde_json::to_string(&command)?;
 
-                writeln!(log_file, "{}", serialized)?;
+                writeln!(writer, "{}", serialized)?;
+                self.persist(&mut writer)?;
+
+                self.stale_bytes += old_location.len + serialized.len() as u64 + 1;
+
+                if self.should_compact()? {
+                    self.compact()?;
+                }
 
                 Ok(())
             }
@@ -214,32 +388,249 @@ impl KvStore {
         }
     }
 
-    fn compact(&mut self) -> Result<()> {
-        let mut active_file_ids: HashSet<u32> = HashSet::default();
+    /// Rejects mutation on a store opened with [`OpenMode::ReadOnly`].
+    fn require_read_write(&self) -> Result<()> {
+        match self.open_mode {
+            OpenMode::ReadWrite => Ok(()),
+            OpenMode::ReadOnly => Err(KvError::KvError(
+                "store was opened read-only".to_owned(),
+            )),
+        }
+    }
+
+    /// Pushes a just-written log append out to stable storage according to
+    /// `self.durability`: `None` leaves it in the OS page cache, `Flush`
+    /// drains the `BufWriter`, and `Fsync` additionally `sync_data`s the
+    /// underlying file so the write survives a crash. In every mode it also
+    /// removes any hint file, which no longer covers this append.
+    fn persist(&self, writer: &mut io::BufWriter<File>) -> Result<()> {
+        let _ = std::fs::remove_file(hint_path(&self.path));
+
+        if self.durability == DurabilityMode::None {
+            return Ok(());
+        }
+
+        writer.flush()?;
+
+        if self.durability == DurabilityMode::Fsync {
+            writer.get_ref().sync_data()?;
+        }
+
+        Ok(())
+    }
+
+    fn read_value(&self, location: &KvLocation) -> Result<V> {
+        let log_file = file_path(&self.path, location.file_id);
+        let mut buffered_reader = io::BufReader::new(File::open(log_file)?);
+
+        buffered_reader.seek(io::SeekFrom::Start(location.offset))?;
+
+        let mut line = String::default();
+        buffered_reader.read_line(&mut line)?;
+
+        let command: KvCommand<K, V> = serde_json::from_str(&line.trim())?;
+
+        match command {
+            KvCommand::Set(_key, value) => Ok(value),
+            _ => Err(KvError::KvError("Inconsistent backing storage".to_string())),
+        }
+    }
 
-        for location in self.cache.values() {
-            active_file_ids.insert(location.file_id);
+    /// Rewrites every live key into a fresh log segment, dropping stale
+    /// `Set`/`Rm` entries, then removes every log file that no longer
+    /// appears in the rebuilt index.
+    ///
+    /// The new segment and its hint file are built at `*.tmp` paths,
+    /// `sync_all`'d, then atomically `rename`d into place before any old
+    /// segment is deleted. A crash at any point during this leaves either
+    /// the original, untouched log files or a fully-formed new segment plus
+    /// hint — never a half-written one.
+    fn compact(&mut self) -> Result<()> {
+        let new_file_id = self.current_file_id + 1;
+        let new_log_path = file_path(&self.path, new_file_id);
+        let tmp_log_path = tmp_path(&new_log_path);
+        let new_log_file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&tmp_log_path)?;
+        let mut writer = io::BufWriter::new(new_log_file);
+
+        let mut rebuilt_cache = HashMap::with_capacity(self.cache.len());
+        let mut offset = 0u64;
+
+        for (key, location) in self.cache.iter() {
+            let value = self.read_value(location)?;
+            let command = KvCommand::Set(key.clone(), value);
+            let serialized = serde_json::to_string(&command)?;
+
+            writeln!(writer, "{}", serialized)?;
+
+            let len = serialized.len() as u64 + 1;
+            rebuilt_cache.insert(
+                key.clone(),
+                KvLocation {
+                    file_id: new_file_id,
+                    offset,
+                    len,
+                },
+            );
+            offset += len;
         }
 
-        for inactive_id in self
-            .file_ids
-            .difference(&active_file_ids)
-            .cloned()
-            .collect::<Vec<u32>>()
-        {
-            std::fs::remove_file(file_path(&self.path, inactive_id))?;
-            self.file_ids.remove(&inactive_id);
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        drop(writer);
+        std::fs::rename(&tmp_log_path, &new_log_path)?;
+
+        let mut stale_file_ids = self.file_ids.clone();
+        stale_file_ids.insert(self.current_file_id);
+        stale_file_ids.remove(&new_file_id);
+
+        self.cache = rebuilt_cache;
+        self.file_ids = HashSet::from_iter(vec![new_file_id]);
+        self.current_file_id = new_file_id;
+        self.stale_bytes = 0;
+
+        self.write_hint()?;
+
+        for old_id in stale_file_ids {
+            std::fs::remove_file(file_path(&self.path, old_id))?;
         }
 
         Ok(())
     }
+
+    /// Snapshots the in-memory index to the hint file so the next `open`
+    /// can skip replaying every segment up to `current_file_id`. Written to
+    /// a `*.tmp` path and `sync_all`'d before the atomic `rename` into
+    /// place, so a crash mid-write never leaves a corrupt hint behind.
+    fn write_hint(&self) -> Result<()> {
+        let hint = HintFile {
+            highest_file_id: self.current_file_id,
+            index: self
+                .cache
+                .iter()
+                .map(|(key, location)| (key.clone(), location.clone()))
+                .collect(),
+            stale_bytes: self.stale_bytes,
+        };
+        let path = hint_path(&self.path);
+        let tmp_path = tmp_path(&path);
+
+        let file = File::create(&tmp_path)?;
+        serde_json::to_writer(&file, &hint)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, &path)?;
+
+        Ok(())
+    }
+
+    fn should_compact(&self) -> Result<bool> {
+        let total_bytes = self.total_disk_bytes()?;
+        Ok(total_bytes > 0 && self.stale_bytes as f64 / total_bytes as f64 >= COMPACTION_THRESHOLD)
+    }
+
+    fn total_disk_bytes(&self) -> Result<u64> {
+        let mut file_ids = self.file_ids.clone();
+        file_ids.insert(self.current_file_id);
+
+        let mut total = 0;
+        for file_id in file_ids {
+            if let Ok(metadata) = std::fs::metadata(file_path(&self.path, file_id)) {
+                total += metadata.len();
+            }
+        }
+
+        Ok(total)
+    }
+}
+
+impl<K, V> Drop for KvStore<K, V>
+where
+    K: Serialize + DeserializeOwned + Hash + Eq + Clone,
+    V: Serialize + DeserializeOwned,
+{
+    /// Persists the index as a hint file so the next `open` can skip a full
+    /// log replay. Best-effort: a failure here just costs the next `open`
+    /// a full replay, it doesn't lose any committed data. Skipped for a
+    /// read-only store, which never mutated `cache` in the first place.
+    fn drop(&mut self) {
+        if self.open_mode == OpenMode::ReadWrite {
+            let _ = self.write_hint();
+        }
+    }
+}
+
+fn load_hint<K>(path: &PathBuf) -> Option<(HashMap<K, KvLocation>, u32, u64)>
+where
+    K: DeserializeOwned + Hash + Eq,
+{
+    let file = File::open(hint_path(path)).ok()?;
+    let hint: HintFile<K> = serde_json::from_reader(file).ok()?;
+
+    Some((
+        hint.index.into_iter().collect(),
+        hint.highest_file_id,
+        hint.stale_bytes,
+    ))
 }
 
-fn update_cache(
-    mut cache: HashMap<String, KvLocation>,
+fn hint_path(path: &PathBuf) -> PathBuf {
+    path.join("index.hint")
+}
+
+fn lock_path(path: &PathBuf) -> PathBuf {
+    path.join("LOCK")
+}
+
+/// Takes an advisory lock on `lock_file` appropriate for `open_mode`.
+/// Returns `Ok(true)` once the lock is held, `Ok(false)` if the filesystem
+/// doesn't support advisory locking at all (so the caller can't rely on it
+/// being held and should fall back to a full replay), or an error if the
+/// lock is genuinely held incompatibly by another process.
+fn acquire_lock(lock_file: &File, open_mode: OpenMode) -> Result<bool> {
+    // Fully qualified: some toolchains now ship an inherent `File::try_lock_shared`
+    // (native file locking) that would otherwise shadow `fs2::FileExt`'s method
+    // of the same name and return a different error type than `try_lock_exclusive`.
+    let result = match open_mode {
+        OpenMode::ReadWrite => fs2::FileExt::try_lock_exclusive(lock_file),
+        OpenMode::ReadOnly => fs2::FileExt::try_lock_shared(lock_file),
+    };
+
+    match result {
+        Ok(()) => Ok(true),
+        Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => Err(KvError::KvError(
+            "store is locked by another process".to_owned(),
+        )),
+        Err(ref err) if is_locking_unsupported(err) => Ok(false),
+        Err(err) => Err(KvError::IoError(err)),
+    }
+}
+
+fn is_locking_unsupported(err: &io::Error) -> bool {
+    matches!(err.raw_os_error(), Some(ENOLCK) | Some(ENOSYS) | Some(EOPNOTSUPP))
+}
+
+/// Scratch path a file is written and `sync_all`'d to before being
+/// atomically `rename`d to `path`, so an interrupted write never leaves a
+/// partial file at the real path.
+fn tmp_path(path: &PathBuf) -> PathBuf {
+    let mut os_string = path.as_os_str().to_owned();
+    os_string.push(".tmp");
+    PathBuf::from(os_string)
+}
+
+fn update_cache<K, V>(
+    mut cache: HashMap<K, KvLocation>,
+    mut stale_bytes: u64,
     log_id: u32,
     path: &PathBuf,
-) -> Result<HashMap<String, KvLocation>> {
+) -> Result<(HashMap<K, KvLocation>, u64)>
+where
+    K: DeserializeOwned + Hash + Eq,
+    V: DeserializeOwned,
+{
     let log_path = file_path(&path, log_id);
     let log_file = File::open(&log_path)?;
     let mut buffered_reader = io::BufReader::new(log_file);
@@ -252,25 +643,34 @@ fn update_cache(
             break;
         }
 
-        let command = serde_json::from_str(&line.trim())?;
+        let command: KvCommand<K, V> = serde_json::from_str(&line.trim())?;
+        let next_offset = buffered_reader.stream_position()?;
+        let len = next_offset - offset;
+
         match command {
             KvCommand::Set(key, _value) => {
                 let location = KvLocation {
                     file_id: log_id,
                     offset,
+                    len,
                 };
-                cache.insert(key, location);
+                if let Some(old_location) = cache.insert(key, location) {
+                    stale_bytes += old_location.len;
+                }
             }
             KvCommand::Rm(key) => {
-                cache.remove(&key);
+                if let Some(old_location) = cache.remove(&key) {
+                    stale_bytes += old_location.len;
+                }
+                stale_bytes += len;
             }
             KvCommand::Get(_) => (),
         }
-        offset = buffered_reader.stream_position()?;
+        offset = next_offset;
         line = String::default();
     }
 
-    Ok(cache)
+    Ok((cache, stale_bytes))
 }
 
 fn load_file_ids(path: &PathBuf) -> Result<Vec<u32>> {
@@ -0,0 +1,60 @@
+//! Wire protocol shared by [`crate::client`] and [`crate::server`]. Each
+//! message is a 4-byte big-endian length prefix followed by that many bytes
+//! of JSON, so a reader always knows exactly how much to read before
+//! deserializing.
+
+use crate::{KvError, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+/// Largest message this protocol will allocate a buffer for. Generous for
+/// any real `Get`/`Set`/`Remove` request, but small enough that a bogus or
+/// hostile length prefix can't make the server commit to a multi-gigabyte
+/// allocation.
+const MAX_MESSAGE_LEN: usize = 8 * 1024 * 1024; // 8MB
+
+/// A request sent from a [`crate::client::KvsClient`] to a
+/// [`crate::server::KvsServer`], mirroring the CLI's `get`/`set`/`rm`.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) enum Request {
+    Get { key: String },
+    Set { key: String, value: String },
+    Remove { key: String },
+}
+
+/// The server's reply to a [`Request`].
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) enum Response {
+    /// The request succeeded; carries the looked-up value for `Get`, or
+    /// `None` for `Set`/`Remove`.
+    Ok(Option<String>),
+    /// The request failed; the string is the failed `KvError`'s `Display`
+    /// output.
+    Err(String),
+}
+
+pub(crate) fn write_message(writer: &mut impl Write, payload: &[u8]) -> Result<()> {
+    writer.write_all(&(payload.len() as u32).to_be_bytes())?;
+    writer.write_all(payload)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+pub(crate) fn read_message(reader: &mut impl Read) -> Result<Vec<u8>> {
+    let mut len_buf = [0u8; 4];
+    reader.read_exact(&mut len_buf)?;
+    let len = u32::from_be_bytes(len_buf) as usize;
+
+    if len > MAX_MESSAGE_LEN {
+        return Err(KvError::KvError(format!(
+            "message length {} exceeds the {} byte limit",
+            len, MAX_MESSAGE_LEN
+        )));
+    }
+
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload)?;
+
+    Ok(payload)
+}
@@ -0,0 +1,93 @@
+//! A TCP server that exposes a `KvStore<String, String>` to [`crate::client::KvsClient`]s.
+
+use crate::protocol::{read_message, write_message, Request, Response};
+use crate::{KvError, KvStore, Result};
+use std::io;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+/// Serves a `KvStore<String, String>` over TCP, one connection at a time.
+/// Each connection may carry any number of requests, answered in order
+/// until the client closes it.
+pub struct KvsServer {
+    store: KvStore<String, String>,
+}
+
+impl KvsServer {
+    /// Wraps an already-open store for serving.
+    pub fn new(store: KvStore<String, String>) -> KvsServer {
+        KvsServer { store }
+    }
+
+    /// Binds `addr` and serves requests until the listener itself fails.
+    /// ```rust,no_run
+    /// use kvs::{KvStore, KvsServer};
+    /// use std::path::Path;
+    /// let store = KvStore::<String, String>::open(Path::new(".")).unwrap();
+    /// KvsServer::new(store).run("127.0.0.1:4000").unwrap();
+    /// ```
+    pub fn run<A: ToSocketAddrs>(self, addr: A) -> Result<()> {
+        let listener = TcpListener::bind(addr)?;
+
+        self.serve_listener(listener)
+    }
+
+    /// Serves requests on an already-bound `listener` until it fails. Handy
+    /// for callers (tests, mainly) that need to know the listener is bound
+    /// and ready before anything else happens, since `TcpListener::bind`
+    /// itself picks the port when given `:0`.
+    ///
+    /// A single connection failing (a malformed request, a client
+    /// disconnecting mid-read) is logged and skipped rather than tearing
+    /// down the whole server.
+    pub fn serve_listener(mut self, listener: TcpListener) -> Result<()> {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    eprintln!("kvs-server: error accepting connection: {}", err);
+                    continue;
+                }
+            };
+
+            if let Err(err) = self.handle_connection(stream) {
+                eprintln!("kvs-server: error serving connection: {}", err);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_connection(&mut self, mut stream: TcpStream) -> Result<()> {
+        loop {
+            let request = match read_message(&mut stream) {
+                Ok(request) => request,
+                Err(KvError::IoError(ref err)) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                    return Ok(())
+                }
+                Err(err) => return Err(err),
+            };
+            self.handle_request(&mut stream, &request)?;
+        }
+    }
+
+    fn handle_request(&mut self, stream: &mut TcpStream, request: &[u8]) -> Result<()> {
+        let request: Request = serde_json::from_slice(request)?;
+
+        let response = match request {
+            Request::Get { key } => match self.store.get(key) {
+                Ok(value) => Response::Ok(value),
+                Err(err) => Response::Err(err.to_string()),
+            },
+            Request::Set { key, value } => match self.store.set(key, value) {
+                Ok(()) => Response::Ok(None),
+                Err(err) => Response::Err(err.to_string()),
+            },
+            Request::Remove { key } => match self.store.remove(key) {
+                Ok(()) => Response::Ok(None),
+                Err(err) => Response::Err(err.to_string()),
+            },
+        };
+
+        write_message(stream, &serde_json::to_vec(&response)?)
+    }
+}
@@ -0,0 +1,37 @@
+use kvs::KvStore;
+use tempfile::tempdir;
+
+/// Overwriting one key many times makes almost everything ever written
+/// stale, which should repeatedly trigger `compact()`'s ratio-based
+/// trigger and keep on-disk size bounded rather than growing with every
+/// write.
+#[test]
+fn repeated_overwrites_are_compacted_away() {
+    let dir = tempdir().unwrap();
+    let mut store = KvStore::<String, String>::open(dir.path()).unwrap();
+
+    for i in 0..20_000 {
+        store
+            .set("key".to_owned(), format!("value-{}", i))
+            .unwrap();
+    }
+
+    assert_eq!(
+        store.get("key".to_owned()).unwrap(),
+        Some("value-19999".to_owned())
+    );
+
+    let on_disk_bytes: u64 = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().metadata().unwrap().len())
+        .sum();
+
+    // Each uncompacted `Set` is tens of bytes; 20,000 of them would be
+    // several hundred KB if none were ever reclaimed. Compaction should
+    // leave only the live key(s) behind.
+    assert!(
+        on_disk_bytes < 10_000,
+        "expected compaction to bound disk usage, found {} bytes on disk",
+        on_disk_bytes
+    );
+}
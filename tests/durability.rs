@@ -0,0 +1,52 @@
+use kvs::{DurabilityMode, KvStore};
+use tempfile::tempdir;
+
+/// All three `DurabilityMode`s should be transparent to the data a caller
+/// reads back; they only change how aggressively writes are pushed to
+/// stable storage, never whether a read-after-write sees its own write.
+#[test]
+fn every_durability_mode_round_trips_reads() {
+    for mode in [
+        DurabilityMode::None,
+        DurabilityMode::Flush,
+        DurabilityMode::Fsync,
+    ] {
+        let dir = tempdir().unwrap();
+        let mut store = KvStore::<String, String>::open_with_durability(dir.path(), mode).unwrap();
+
+        store.set("key".to_owned(), "value".to_owned()).unwrap();
+        assert_eq!(
+            store.get("key".to_owned()).unwrap(),
+            Some("value".to_owned())
+        );
+    }
+}
+
+/// Compaction writes its new segment and hint file to `*.tmp` paths and
+/// renames them into place; no `.tmp` file should ever be left behind once
+/// a compaction has actually run.
+#[test]
+fn compaction_leaves_no_tmp_files_behind() {
+    let dir = tempdir().unwrap();
+    let mut store = KvStore::<String, String>::open(dir.path()).unwrap();
+
+    for i in 0..20_000 {
+        store
+            .set("key".to_owned(), format!("value-{}", i))
+            .unwrap();
+    }
+
+    let leftover_tmp_files = std::fs::read_dir(dir.path())
+        .unwrap()
+        .filter(|entry| {
+            entry
+                .as_ref()
+                .unwrap()
+                .path()
+                .extension()
+                .map_or(false, |ext| ext == "tmp")
+        })
+        .count();
+
+    assert_eq!(leftover_tmp_files, 0);
+}
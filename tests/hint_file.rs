@@ -0,0 +1,45 @@
+use kvs::KvStore;
+use tempfile::tempdir;
+
+/// A clean shutdown writes `index.hint`, and reopening the store should
+/// reflect exactly the same live keys without replaying every log segment
+/// by hand.
+#[test]
+fn reopen_after_clean_shutdown_preserves_data() {
+    let dir = tempdir().unwrap();
+
+    {
+        let mut store = KvStore::<String, String>::open(dir.path()).unwrap();
+        store.set("a".to_owned(), "1".to_owned()).unwrap();
+        store.set("b".to_owned(), "2".to_owned()).unwrap();
+        store.remove("a".to_owned()).unwrap();
+        // Dropped here, which persists the hint file.
+    }
+
+    assert!(dir.path().join("index.hint").exists());
+
+    let store = KvStore::<String, String>::open(dir.path()).unwrap();
+    assert_eq!(store.get("a".to_owned()).unwrap(), None);
+    assert_eq!(store.get("b".to_owned()).unwrap(), Some("2".to_owned()));
+}
+
+/// Writes made after a hint was written (i.e. in a later process run that
+/// opened from that hint) must still show up on the next open.
+#[test]
+fn writes_after_a_hint_was_taken_are_not_lost() {
+    let dir = tempdir().unwrap();
+
+    {
+        let mut store = KvStore::<String, String>::open(dir.path()).unwrap();
+        store.set("a".to_owned(), "1".to_owned()).unwrap();
+    }
+
+    {
+        let mut store = KvStore::<String, String>::open(dir.path()).unwrap();
+        store.set("b".to_owned(), "2".to_owned()).unwrap();
+    }
+
+    let store = KvStore::<String, String>::open(dir.path()).unwrap();
+    assert_eq!(store.get("a".to_owned()).unwrap(), Some("1".to_owned()));
+    assert_eq!(store.get("b".to_owned()).unwrap(), Some("2".to_owned()));
+}
@@ -0,0 +1,45 @@
+use kvs::KvStore;
+use tempfile::tempdir;
+
+/// A second read-write `open` against a directory another process already
+/// holds open should fail loudly instead of silently corrupting the index.
+#[test]
+fn concurrent_read_write_open_is_rejected() {
+    let dir = tempdir().unwrap();
+    let _writer = KvStore::<String, String>::open(dir.path()).unwrap();
+
+    let err = KvStore::<String, String>::open(dir.path()).unwrap_err();
+    assert!(err.to_string().contains("locked"));
+}
+
+/// A read-only open takes a shared lock, so it's rejected while an
+/// exclusive read-write lock is held, but succeeds once that lock is
+/// released.
+#[test]
+fn read_only_open_is_rejected_until_the_read_write_lock_clears() {
+    let dir = tempdir().unwrap();
+
+    {
+        let _writer = KvStore::<String, String>::open(dir.path()).unwrap();
+        let err = KvStore::<String, String>::open_read_only(dir.path()).unwrap_err();
+        assert!(err.to_string().contains("locked"));
+    }
+
+    let _reader = KvStore::<String, String>::open_read_only(dir.path()).unwrap();
+}
+
+/// A store opened read-only must reject mutation, even though it replayed
+/// the same on-disk state a read-write open would have.
+#[test]
+fn read_only_store_rejects_set_and_remove() {
+    let dir = tempdir().unwrap();
+    {
+        let mut store = KvStore::<String, String>::open(dir.path()).unwrap();
+        store.set("a".to_owned(), "1".to_owned()).unwrap();
+    }
+
+    let mut store = KvStore::<String, String>::open_read_only(dir.path()).unwrap();
+    assert_eq!(store.get("a".to_owned()).unwrap(), Some("1".to_owned()));
+    assert!(store.set("a".to_owned(), "2".to_owned()).is_err());
+    assert!(store.remove("a".to_owned()).is_err());
+}
@@ -0,0 +1,38 @@
+use kvs::{KvStore, KvsClient, KvsServer};
+use std::net::TcpListener;
+use std::thread;
+use tempfile::tempdir;
+
+/// A `KvsClient` should be able to `set`/`get`/`remove` against a
+/// `KvsServer` exactly as a local `KvStore` would, round-tripping through
+/// the length-prefixed wire protocol.
+#[test]
+fn client_server_round_trip() {
+    let dir = tempdir().unwrap();
+    let store = KvStore::<String, String>::open(dir.path()).unwrap();
+
+    // Bind in this thread so the listener is actually accepting by the time
+    // `addr` is handed to the client; the server thread just takes over the
+    // already-bound listener instead of racing to rebind the same address.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        KvsServer::new(store).serve_listener(listener).unwrap();
+    });
+
+    let mut client = KvsClient::connect(addr).unwrap();
+
+    assert_eq!(client.get("key".to_owned()).unwrap(), None);
+
+    client
+        .set("key".to_owned(), "value".to_owned())
+        .unwrap();
+    assert_eq!(
+        client.get("key".to_owned()).unwrap(),
+        Some("value".to_owned())
+    );
+
+    client.remove("key".to_owned()).unwrap();
+    assert_eq!(client.get("key".to_owned()).unwrap(), None);
+}